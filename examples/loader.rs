@@ -27,6 +27,159 @@ pub struct Vulkan {
     pub commands: vk::LibraryCommands,
 }
 
+/// Declares a named, `bool`-per-extension set with `from_enumerated`/`to_cstr_list`
+/// and `difference`/`intersection` helpers, so requesting an unsupported extension is
+/// checkable data instead of a runtime `CreateInstance`/`CreateDevice` failure.
+macro_rules! extensions {
+    ($name:ident { $($field:ident => $ext:expr),* $(,)? }) => {
+        #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+        pub struct $name {
+            $(pub $field: bool,)*
+        }
+
+        impl $name {
+            const NAMES: &'static [(&'static str, fn(&Self) -> bool)] = &[
+                $(($ext, |s: &Self| s.$field),)*
+            ];
+
+            pub fn from_enumerated(props: &[VkExtensionProperties]) -> Self {
+                let mut set = Self::default();
+                for p in props {
+                    let name = p.extension_name();
+                    $(
+                        if name == $ext {
+                            set.$field = true;
+                        }
+                    )*
+                }
+                set
+            }
+
+            pub fn to_cstr_list(&self) -> Vec<CString> {
+                Self::NAMES
+                    .iter()
+                    .filter(|(_, get)| get(self))
+                    .map(|(name, _)| CString::new(*name).unwrap())
+                    .collect()
+            }
+
+            pub fn difference(&self, other: &Self) -> Self {
+                let mut set = Self::default();
+                $(set.$field = self.$field && !other.$field;)*
+                set
+            }
+
+            pub fn intersection(&self, other: &Self) -> Self {
+                let mut set = Self::default();
+                $(set.$field = self.$field && other.$field;)*
+                set
+            }
+
+            pub fn is_empty(&self) -> bool {
+                $(!self.$field &&)* true
+            }
+        }
+    };
+}
+
+extensions!(InstanceExtensions {
+    khr_surface => "VK_KHR_surface",
+    khr_win32_surface => "VK_KHR_win32_surface",
+    khr_xlib_surface => "VK_KHR_xlib_surface",
+    khr_wayland_surface => "VK_KHR_wayland_surface",
+    ext_debug_utils => "VK_EXT_debug_utils",
+    khr_get_physical_device_properties2 => "VK_KHR_get_physical_device_properties2",
+});
+
+extensions!(DeviceExtensions {
+    khr_swapchain => "VK_KHR_swapchain",
+});
+
+/// Wraps the common negative `VkResult` codes as named variants (plus an
+/// `Unknown(i32)` fallback) so callers can match on a typed error instead of
+/// comparing against raw `vk::Result` integer constants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VulkanError {
+    OutOfHostMemory,
+    OutOfDeviceMemory,
+    InitializationFailed,
+    DeviceLost,
+    MemoryMapFailed,
+    LayerNotPresent,
+    ExtensionNotPresent,
+    FeatureNotPresent,
+    IncompatibleDriver,
+    TooManyObjects,
+    FormatNotSupported,
+    FragmentedPool,
+    SurfaceLostKHR,
+    NativeWindowInUseKHR,
+    OutOfDateKHR,
+    IncompatibleDisplayKHR,
+    Unknown(i32),
+}
+
+impl fmt::Display for VulkanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VulkanError::OutOfHostMemory => write!(f, "out of host memory"),
+            VulkanError::OutOfDeviceMemory => write!(f, "out of device memory"),
+            VulkanError::InitializationFailed => write!(f, "initialization failed"),
+            VulkanError::DeviceLost => write!(f, "device lost"),
+            VulkanError::MemoryMapFailed => write!(f, "memory map failed"),
+            VulkanError::LayerNotPresent => write!(f, "layer not present"),
+            VulkanError::ExtensionNotPresent => write!(f, "extension not present"),
+            VulkanError::FeatureNotPresent => write!(f, "feature not present"),
+            VulkanError::IncompatibleDriver => write!(f, "incompatible driver"),
+            VulkanError::TooManyObjects => write!(f, "too many objects"),
+            VulkanError::FormatNotSupported => write!(f, "format not supported"),
+            VulkanError::FragmentedPool => write!(f, "fragmented pool"),
+            VulkanError::SurfaceLostKHR => write!(f, "surface lost"),
+            VulkanError::NativeWindowInUseKHR => write!(f, "native window in use"),
+            VulkanError::OutOfDateKHR => write!(f, "swapchain out of date"),
+            VulkanError::IncompatibleDisplayKHR => write!(f, "incompatible display"),
+            VulkanError::Unknown(code) => write!(f, "unknown vulkan error (code {})", code),
+        }
+    }
+}
+
+impl std::error::Error for VulkanError {}
+
+impl From<vk::Result> for VulkanError {
+    fn from(result: vk::Result) -> Self {
+        match result {
+            vk::ERROR_OUT_OF_HOST_MEMORY => VulkanError::OutOfHostMemory,
+            vk::ERROR_OUT_OF_DEVICE_MEMORY => VulkanError::OutOfDeviceMemory,
+            vk::ERROR_INITIALIZATION_FAILED => VulkanError::InitializationFailed,
+            vk::ERROR_DEVICE_LOST => VulkanError::DeviceLost,
+            vk::ERROR_MEMORY_MAP_FAILED => VulkanError::MemoryMapFailed,
+            vk::ERROR_LAYER_NOT_PRESENT => VulkanError::LayerNotPresent,
+            vk::ERROR_EXTENSION_NOT_PRESENT => VulkanError::ExtensionNotPresent,
+            vk::ERROR_FEATURE_NOT_PRESENT => VulkanError::FeatureNotPresent,
+            vk::ERROR_INCOMPATIBLE_DRIVER => VulkanError::IncompatibleDriver,
+            vk::ERROR_TOO_MANY_OBJECTS => VulkanError::TooManyObjects,
+            vk::ERROR_FORMAT_NOT_SUPPORTED => VulkanError::FormatNotSupported,
+            vk::ERROR_FRAGMENTED_POOL => VulkanError::FragmentedPool,
+            vk::ERROR_SURFACE_LOST_KHR => VulkanError::SurfaceLostKHR,
+            vk::ERROR_NATIVE_WINDOW_IN_USE_KHR => VulkanError::NativeWindowInUseKHR,
+            vk::ERROR_OUT_OF_DATE_KHR => VulkanError::OutOfDateKHR,
+            vk::ERROR_INCOMPATIBLE_DISPLAY_KHR => VulkanError::IncompatibleDisplayKHR,
+            code => VulkanError::Unknown(code),
+        }
+    }
+}
+
+/// Converts a raw `VkResult` to `Ok(())` on `vk::SUCCESS`/`vk::SUBOPTIMAL_KHR` and a
+/// typed `VulkanError` otherwise. Used internally wherever a command's return code
+/// previously had to be compared against `vk::SUCCESS` by hand.
+fn check(result: vk::Result) -> Result<(), VulkanError> {
+    if result == vk::SUCCESS || result == vk::SUBOPTIMAL_KHR {
+        Ok(())
+    } else {
+        Err(result.into())
+    }
+}
+
 #[repr(transparent)]
 pub struct VkExtensionProperties(pub vk::ExtensionProperties);
 
@@ -84,6 +237,150 @@ impl Clone for VkLayerProperties {
     }
 }
 
+pub type DebugCallback =
+    Box<dyn Fn(vk::DebugUtilsMessageSeverityFlagsEXT, vk::DebugUtilsMessageTypeFlagsEXT, &str, &str)>;
+
+type FnCreateDebugUtilsMessengerEXT = unsafe extern "system" fn(
+    instance: vk::Instance,
+    pCreateInfo: *const vk::DebugUtilsMessengerCreateInfoEXT,
+    pAllocator: *const vk::AllocationCallbacks,
+    pMessenger: *mut vk::DebugUtilsMessengerEXT,
+) -> vk::Result;
+
+type FnDestroyDebugUtilsMessengerEXT = unsafe extern "system" fn(
+    instance: vk::Instance,
+    messenger: vk::DebugUtilsMessengerEXT,
+    pAllocator: *const vk::AllocationCallbacks,
+);
+
+unsafe extern "system" fn debug_messenger_trampoline(
+    message_severity: vk::DebugUtilsMessageSeverityFlagsEXT,
+    message_types: vk::DebugUtilsMessageTypeFlagsEXT,
+    callback_data: *const vk::DebugUtilsMessengerCallbackDataEXT,
+    user_data: *mut std::ffi::c_void,
+) -> vk::Bool32 {
+    let callback = &*(user_data as *const DebugCallback);
+    let id_name = if (*callback_data).pMessageIdName.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr((*callback_data).pMessageIdName)
+            .to_string_lossy()
+            .into_owned()
+    };
+    let message = CStr::from_ptr((*callback_data).pMessage)
+        .to_string_lossy()
+        .into_owned();
+    callback(message_severity, message_types, &id_name, &message);
+    vk::FALSE
+}
+
+/// Formats `pMessageIdName` and `pMessage` into a single log line, prefixed with the
+/// message severity. Suitable as the default argument to `DebugMessengerInfo::new`.
+pub fn default_debug_callback(
+    severity: vk::DebugUtilsMessageSeverityFlagsEXT,
+    _message_types: vk::DebugUtilsMessageTypeFlagsEXT,
+    id_name: &str,
+    message: &str,
+) {
+    let level = if severity & vk::DEBUG_UTILS_MESSAGE_SEVERITY_ERROR_BIT_EXT != 0 {
+        "error"
+    } else if severity & vk::DEBUG_UTILS_MESSAGE_SEVERITY_WARNING_BIT_EXT != 0 {
+        "warn"
+    } else if severity & vk::DEBUG_UTILS_MESSAGE_SEVERITY_INFO_BIT_EXT != 0 {
+        "info"
+    } else {
+        "verbose"
+    };
+    println!("[vulkan:{}] {}: {}", level, id_name, message);
+}
+
+/// Severity and message-type flags plus the user callback backing a `DebugMessenger`.
+///
+/// Also usable standalone to populate `InstanceCreateInfo.pNext` so that messages
+/// emitted during `vkCreateInstance`/`vkDestroyInstance` are captured.
+pub struct DebugMessengerInfo {
+    pub severity: vk::DebugUtilsMessageSeverityFlagsEXT,
+    pub message_type: vk::DebugUtilsMessageTypeFlagsEXT,
+    pub callback: DebugCallback,
+}
+
+impl DebugMessengerInfo {
+    pub fn new(callback: DebugCallback) -> Self {
+        DebugMessengerInfo {
+            severity: vk::DEBUG_UTILS_MESSAGE_SEVERITY_ERROR_BIT_EXT
+                | vk::DEBUG_UTILS_MESSAGE_SEVERITY_WARNING_BIT_EXT,
+            message_type: vk::DEBUG_UTILS_MESSAGE_TYPE_GENERAL_BIT_EXT
+                | vk::DEBUG_UTILS_MESSAGE_TYPE_VALIDATION_BIT_EXT
+                | vk::DEBUG_UTILS_MESSAGE_TYPE_PERFORMANCE_BIT_EXT,
+            callback,
+        }
+    }
+
+    fn create_info(&self) -> vk::DebugUtilsMessengerCreateInfoEXT {
+        vk::DebugUtilsMessengerCreateInfoEXT {
+            sType: vk::STRUCTURE_TYPE_DEBUG_UTILS_MESSENGER_CREATE_INFO_EXT,
+            pNext: ptr::null(),
+            flags: 0,
+            messageSeverity: self.severity,
+            messageType: self.message_type,
+            pfnUserCallback: debug_messenger_trampoline,
+            pUserData: &self.callback as *const DebugCallback as *mut _,
+        }
+    }
+}
+
+/// Owns a `VkDebugUtilsMessengerEXT` created via `vkCreateDebugUtilsMessengerEXT`,
+/// loaded manually through `GetInstanceProcAddr` since the extension is not part of
+/// `vk::InstanceCommands`.
+pub struct DebugMessenger {
+    instance: vk::Instance,
+    messenger: vk::DebugUtilsMessengerEXT,
+    destroy_fn: FnDestroyDebugUtilsMessengerEXT,
+    // Boxed so `info.callback`'s address stays stable after `new` returns: it's baked
+    // into `pUserData` and dereferenced by `debug_messenger_trampoline` on every message.
+    info: Box<DebugMessengerInfo>,
+}
+
+impl Drop for DebugMessenger {
+    fn drop(&mut self) {
+        unsafe { (self.destroy_fn)(self.instance, self.messenger, ptr::null()) };
+    }
+}
+
+impl DebugMessenger {
+    pub fn new(
+        get_instance_proc_addr: vk::FnGetInstanceProcAddr,
+        instance: vk::Instance,
+        info: DebugMessengerInfo,
+    ) -> Result<Self, VulkanError> {
+        let create_fn: FnCreateDebugUtilsMessengerEXT = unsafe {
+            let s = get_instance_proc_addr(instance, b"vkCreateDebugUtilsMessengerEXT\0".as_ptr() as _)
+                .ok_or(VulkanError::ExtensionNotPresent)?;
+            mem::transmute(s)
+        };
+        let destroy_fn: FnDestroyDebugUtilsMessengerEXT = unsafe {
+            let s = get_instance_proc_addr(instance, b"vkDestroyDebugUtilsMessengerEXT\0".as_ptr() as _)
+                .ok_or(VulkanError::ExtensionNotPresent)?;
+            mem::transmute(s)
+        };
+
+        // Box before taking `pUserData`'s address: `info` itself is about to be moved
+        // into the struct we return, but boxing first means that address is the stable
+        // heap location of the box's contents, not this local binding.
+        let info = Box::new(info);
+        let create_info = info.create_info();
+        let mut messenger: vk::DebugUtilsMessengerEXT = 0;
+        check(unsafe { create_fn(instance, &create_info, ptr::null(), &mut messenger) })?;
+
+        Ok(DebugMessenger {
+            instance,
+            messenger,
+            destroy_fn,
+            info,
+        })
+    }
+}
+
 impl Vulkan {
     pub fn new() -> Result<Self, libloading::Error> {
         let maybe_lib = unsafe { libloading::Library::new(VULKAN_LIB) };
@@ -103,60 +400,44 @@ impl Vulkan {
         })
     }
 
-    pub fn enum_extensions(&self) -> Result<Vec<VkExtensionProperties>, vk::Result> {
+    pub fn enum_extensions(&self) -> Result<Vec<VkExtensionProperties>, VulkanError> {
         let mut num_properties: u32 = 0;
-        let result = unsafe {
+        check(unsafe {
             self.commands.EnumerateInstanceExtensionProperties(
                 ptr::null(),
                 &mut num_properties,
                 ptr::null_mut(),
             )
-        };
+        })?;
 
-        if result == vk::SUCCESS {
-            let mut ext_props = vec![VkExtensionProperties::default(); num_properties as _];
-            let result = unsafe {
-                self.commands.EnumerateInstanceExtensionProperties(
-                    ptr::null(),
-                    &mut num_properties,
-                    ext_props.as_mut_ptr() as _,
-                )
-            };
+        let mut ext_props = vec![VkExtensionProperties::default(); num_properties as _];
+        check(unsafe {
+            self.commands.EnumerateInstanceExtensionProperties(
+                ptr::null(),
+                &mut num_properties,
+                ext_props.as_mut_ptr() as _,
+            )
+        })?;
 
-            if result == vk::SUCCESS {
-                Ok(ext_props)
-            } else {
-                Err(result)
-            }
-        } else {
-            Err(result)
-        }
+        Ok(ext_props)
     }
 
-    pub fn enum_layers(&self) -> Result<Vec<VkLayerProperties>, vk::Result> {
+    pub fn enum_layers(&self) -> Result<Vec<VkLayerProperties>, VulkanError> {
         let mut num_properties: u32 = 0;
-        let result = unsafe {
+        check(unsafe {
             self.commands
                 .EnumerateInstanceLayerProperties(&mut num_properties, ptr::null_mut())
-        };
+        })?;
 
-        if result == vk::SUCCESS {
-            let mut layer_props = vec![VkLayerProperties::default(); num_properties as _];
-            let result = unsafe {
-                self.commands.EnumerateInstanceLayerProperties(
-                    &mut num_properties,
-                    layer_props.as_mut_ptr() as _,
-                )
-            };
+        let mut layer_props = vec![VkLayerProperties::default(); num_properties as _];
+        check(unsafe {
+            self.commands.EnumerateInstanceLayerProperties(
+                &mut num_properties,
+                layer_props.as_mut_ptr() as _,
+            )
+        })?;
 
-            if result == vk::SUCCESS {
-                Ok(layer_props)
-            } else {
-                Err(result)
-            }
-        } else {
-            Err(result)
-        }
+        Ok(layer_props)
     }
 }
 
@@ -164,10 +445,13 @@ pub struct Instance {
     pub vk: Vulkan,
     pub instance: vk::Instance,
     pub commands: vk::InstanceCommands,
+    pub debug_messenger: Option<DebugMessenger>,
 }
 
 impl Drop for Instance {
     fn drop(&mut self) {
+        // Drop the messenger before destroying the instance it was created against.
+        self.debug_messenger = None;
         unsafe { self.commands.DestroyInstance(self.instance, ptr::null()) };
     }
 }
@@ -178,8 +462,10 @@ impl Instance {
         app_name: &str,
         engine_name: &str,
         layers: &[&str],
-        extensions: &[&str],
-    ) -> Result<Instance, vk::Result> {
+        extensions: &InstanceExtensions,
+        api_version: (u32, u32, u32),
+        debug_messenger_info: Option<DebugMessengerInfo>,
+    ) -> Result<Instance, VulkanError> {
         let app_name_cstr = CString::new(app_name).unwrap();
         let engine_name_cstr = CString::new(engine_name).unwrap();
 
@@ -190,108 +476,103 @@ impl Instance {
             applicationVersion: 1,
             pEngineName: engine_name_cstr.as_ptr(),
             engineVersion: 1,
-            apiVersion: vk::make_version(1, 2, 133),
+            apiVersion: vk::make_version(api_version.0, api_version.1, api_version.2),
         };
 
         let layers_cstr: Vec<_> = layers.iter().map(|&s| CString::new(s).unwrap()).collect();
-        let extensions_cstr: Vec<_> = extensions
-            .iter()
-            .map(|&s| CString::new(s).unwrap())
-            .collect();
+        let extensions_cstr = extensions.to_cstr_list();
 
         let layers_ptr: Vec<_> = layers_cstr.iter().map(|s| s.as_ptr()).collect();
         let extensions_ptr: Vec<_> = extensions_cstr.iter().map(|s| s.as_ptr()).collect();
 
+        // Building this up-front, even though it is only used for its address below,
+        // lets vkCreateInstance/vkDestroyInstance messages be captured via pNext.
+        let messenger_create_info = debug_messenger_info.as_ref().map(|info| info.create_info());
+
         let instance_info = vk::InstanceCreateInfo {
             sType: vk::STRUCTURE_TYPE_INSTANCE_CREATE_INFO,
-            pNext: ptr::null(),
+            pNext: match &messenger_create_info {
+                Some(info) => info as *const _ as *const std::ffi::c_void,
+                None => ptr::null(),
+            },
             flags: 0,
             pApplicationInfo: &app_info,
             enabledLayerCount: layers.len() as _,
             ppEnabledLayerNames: layers_ptr.as_ptr(),
-            enabledExtensionCount: extensions.len() as _,
+            enabledExtensionCount: extensions_ptr.len() as _,
             ppEnabledExtensionNames: extensions_ptr.as_ptr(),
         };
 
         let mut instance: vk::Instance = 0;
-        let result = unsafe {
+        check(unsafe {
             vk.commands
                 .CreateInstance(&instance_info, ptr::null(), &mut instance)
+        })?;
+
+        let commands = vk::InstanceCommands::new(vk.GetInstanceProcAddr, instance);
+        // Built with `debug_messenger: None` first so that if attaching the messenger
+        // below fails, this `Instance` is already a live local and its `Drop` destroys
+        // `instance` on the way out instead of leaking it.
+        let mut result = Instance {
+            vk,
+            instance,
+            commands,
+            debug_messenger: None,
         };
-
-        match result {
-            vk::SUCCESS => {
-                let commands = vk::InstanceCommands::new(vk.GetInstanceProcAddr, instance);
-                Ok(Instance {
-                    vk,
-                    instance,
-                    commands,
-                })
-            }
-            _ => Err(result),
+        if let Some(info) = debug_messenger_info {
+            result.debug_messenger = Some(DebugMessenger::new(
+                result.vk.GetInstanceProcAddr,
+                result.instance,
+                info,
+            )?);
         }
+        Ok(result)
     }
 
-    fn enum_physical_devices(&self) -> Result<Vec<vk::PhysicalDevice>, vk::Result> {
+    fn enum_physical_devices(&self) -> Result<Vec<vk::PhysicalDevice>, VulkanError> {
         let mut num_devices: u32 = 0;
-        let result = unsafe {
+        check(unsafe {
             self.commands
                 .EnumeratePhysicalDevices(self.instance, &mut num_devices, ptr::null_mut())
-        };
-
-        if result == vk::SUCCESS {
-            let mut devices = vec![vk::PhysicalDevice::default(); num_devices as _];
-            let result = unsafe {
-                self.commands.EnumeratePhysicalDevices(
-                    self.instance,
-                    &mut num_devices,
-                    devices.as_mut_ptr() as _,
-                )
-            };
+        })?;
+
+        let mut devices = vec![vk::PhysicalDevice::default(); num_devices as _];
+        check(unsafe {
+            self.commands.EnumeratePhysicalDevices(
+                self.instance,
+                &mut num_devices,
+                devices.as_mut_ptr() as _,
+            )
+        })?;
 
-            if result == vk::SUCCESS {
-                Ok(devices)
-            } else {
-                Err(result)
-            }
-        } else {
-            Err(result)
-        }
+        Ok(devices)
     }
 
     pub fn enum_physical_device_extensions(
         &self,
         device: vk::PhysicalDevice,
-    ) -> Result<Vec<VkExtensionProperties>, vk::Result> {
+    ) -> Result<Vec<VkExtensionProperties>, VulkanError> {
         let mut num_properties: u32 = 0;
-        let result = unsafe {
+        check(unsafe {
             self.commands.EnumerateDeviceExtensionProperties(
                 device,
                 ptr::null(),
                 &mut num_properties,
                 ptr::null_mut(),
             )
-        };
+        })?;
 
-        if result == vk::SUCCESS {
-            let mut ext_props = vec![VkExtensionProperties::default(); num_properties as _];
-            let result = unsafe {
-                self.commands.EnumerateDeviceExtensionProperties(
-                    device,
-                    ptr::null(),
-                    &mut num_properties,
-                    ext_props.as_mut_ptr() as _,
-                )
-            };
+        let mut ext_props = vec![VkExtensionProperties::default(); num_properties as _];
+        check(unsafe {
+            self.commands.EnumerateDeviceExtensionProperties(
+                device,
+                ptr::null(),
+                &mut num_properties,
+                ext_props.as_mut_ptr() as _,
+            )
+        })?;
 
-            if result == vk::SUCCESS {
-                Ok(ext_props)
-            } else {
-                Err(result)
-            }
-        } else {
-            Err(result)
-        }
+        Ok(ext_props)
     }
 
     pub fn enum_physical_device_queue_family_properties(
@@ -359,6 +640,302 @@ impl Instance {
             properties.assume_init()
         }
     }
+
+    /// Enumerates physical devices and eagerly queries everything `PhysicalDevice`
+    /// caches, so callers don't have to re-query properties/features/memory/queue
+    /// families/extensions for every device on every lookup.
+    pub fn enumerate_physical_devices_cached(&self) -> Result<Vec<PhysicalDevice>, VulkanError> {
+        self.enum_physical_devices()?
+            .into_iter()
+            .map(|handle| {
+                let extensions = self.enum_physical_device_extensions(handle)?;
+                Ok(PhysicalDevice {
+                    handle,
+                    properties: self.get_physical_device_properties(handle),
+                    features: self.get_physical_device_features(handle),
+                    memory_properties: self.get_physical_device_memory_properties(handle),
+                    queue_family_properties: self.enum_physical_device_queue_family_properties(handle),
+                    extensions: DeviceExtensions::from_enumerated(&extensions),
+                })
+            })
+            .collect()
+    }
+
+    /// Queries `vkGetPhysicalDeviceFeatures2KHR` with a `pNext` chain of commonly
+    /// needed extension feature structs. Requires
+    /// `VK_KHR_get_physical_device_properties2` to be enabled on the instance.
+    pub fn get_physical_device_features2(
+        &self,
+        device: vk::PhysicalDevice,
+    ) -> Result<FeaturesChain, VulkanError> {
+        let get_fn: FnGetPhysicalDeviceFeatures2KHR = load_instance_fn(
+            self.vk.GetInstanceProcAddr,
+            self.instance,
+            b"vkGetPhysicalDeviceFeatures2KHR\0",
+        )?;
+        let mut chain = FeaturesChain::new();
+        unsafe { get_fn(device, &mut chain.features2) };
+        Ok(chain)
+    }
+
+    /// Queries `vkGetPhysicalDeviceProperties2KHR` with a `pNext` chain of commonly
+    /// needed extension property structs. Requires
+    /// `VK_KHR_get_physical_device_properties2` to be enabled on the instance.
+    pub fn get_physical_device_properties2(
+        &self,
+        device: vk::PhysicalDevice,
+    ) -> Result<PropertiesChain, VulkanError> {
+        let get_fn: FnGetPhysicalDeviceProperties2KHR = load_instance_fn(
+            self.vk.GetInstanceProcAddr,
+            self.instance,
+            b"vkGetPhysicalDeviceProperties2KHR\0",
+        )?;
+        let mut chain = PropertiesChain::new();
+        unsafe { get_fn(device, &mut chain.properties2) };
+        Ok(chain)
+    }
+}
+
+type FnGetPhysicalDeviceFeatures2KHR =
+    unsafe extern "system" fn(physicalDevice: vk::PhysicalDevice, pFeatures: *mut vk::PhysicalDeviceFeatures2);
+
+type FnGetPhysicalDeviceProperties2KHR = unsafe extern "system" fn(
+    physicalDevice: vk::PhysicalDevice,
+    pProperties: *mut vk::PhysicalDeviceProperties2,
+);
+
+/// Owns the boxed extension feature structs linked into a `VkPhysicalDeviceFeatures2`
+/// `pNext` chain, so the addresses stay stable while the chain is queried and can
+/// later be re-fed (the enabled subset) through `DeviceCreateInfo.pNext`.
+pub struct FeaturesChain {
+    pub features2: vk::PhysicalDeviceFeatures2,
+    pub descriptor_indexing: Box<vk::PhysicalDeviceDescriptorIndexingFeatures>,
+    pub buffer_device_address: Box<vk::PhysicalDeviceBufferDeviceAddressFeatures>,
+    pub timeline_semaphore: Box<vk::PhysicalDeviceTimelineSemaphoreFeatures>,
+}
+
+impl FeaturesChain {
+    fn new() -> Self {
+        let mut timeline_semaphore: Box<vk::PhysicalDeviceTimelineSemaphoreFeatures> =
+            Box::new(unsafe { std::mem::zeroed() });
+        timeline_semaphore.sType = vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_TIMELINE_SEMAPHORE_FEATURES;
+        timeline_semaphore.pNext = ptr::null_mut();
+
+        let mut buffer_device_address: Box<vk::PhysicalDeviceBufferDeviceAddressFeatures> =
+            Box::new(unsafe { std::mem::zeroed() });
+        buffer_device_address.sType = vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_BUFFER_DEVICE_ADDRESS_FEATURES;
+        buffer_device_address.pNext =
+            timeline_semaphore.as_mut() as *mut _ as *mut std::ffi::c_void;
+
+        let mut descriptor_indexing: Box<vk::PhysicalDeviceDescriptorIndexingFeatures> =
+            Box::new(unsafe { std::mem::zeroed() });
+        descriptor_indexing.sType = vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_DESCRIPTOR_INDEXING_FEATURES;
+        descriptor_indexing.pNext =
+            buffer_device_address.as_mut() as *mut _ as *mut std::ffi::c_void;
+
+        let features2 = vk::PhysicalDeviceFeatures2 {
+            sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_FEATURES_2,
+            pNext: descriptor_indexing.as_mut() as *mut _ as *mut std::ffi::c_void,
+            features: unsafe { std::mem::zeroed() },
+        };
+
+        FeaturesChain {
+            features2,
+            descriptor_indexing,
+            buffer_device_address,
+            timeline_semaphore,
+        }
+    }
+}
+
+/// Owns the boxed extension property structs linked into a
+/// `VkPhysicalDeviceProperties2` `pNext` chain; mirrors `FeaturesChain`.
+pub struct PropertiesChain {
+    pub properties2: vk::PhysicalDeviceProperties2,
+    pub descriptor_indexing: Box<vk::PhysicalDeviceDescriptorIndexingPropertiesEXT>,
+    pub driver: Box<vk::PhysicalDeviceDriverProperties>,
+}
+
+impl PropertiesChain {
+    fn new() -> Self {
+        let mut driver: Box<vk::PhysicalDeviceDriverProperties> = Box::new(unsafe { std::mem::zeroed() });
+        driver.sType = vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_DRIVER_PROPERTIES;
+        driver.pNext = ptr::null_mut();
+
+        let mut descriptor_indexing: Box<vk::PhysicalDeviceDescriptorIndexingPropertiesEXT> =
+            Box::new(unsafe { std::mem::zeroed() });
+        descriptor_indexing.sType = vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_DESCRIPTOR_INDEXING_PROPERTIES_EXT;
+        descriptor_indexing.pNext = driver.as_mut() as *mut _ as *mut std::ffi::c_void;
+
+        let properties2 = vk::PhysicalDeviceProperties2 {
+            sType: vk::STRUCTURE_TYPE_PHYSICAL_DEVICE_PROPERTIES_2,
+            pNext: descriptor_indexing.as_mut() as *mut _ as *mut std::ffi::c_void,
+            properties: unsafe { std::mem::zeroed() },
+        };
+
+        PropertiesChain {
+            properties2,
+            descriptor_indexing,
+            driver,
+        }
+    }
+}
+
+/// Eagerly-cached properties/features/memory/queue-families/extensions for one
+/// physical device, so selection logic doesn't have to re-query the driver.
+pub struct PhysicalDevice {
+    pub handle: vk::PhysicalDevice,
+    pub properties: vk::PhysicalDeviceProperties,
+    pub features: vk::PhysicalDeviceFeatures,
+    pub memory_properties: vk::PhysicalDeviceMemoryProperties,
+    pub queue_family_properties: Vec<vk::QueueFamilyProperties>,
+    pub extensions: DeviceExtensions,
+}
+
+impl PhysicalDevice {
+    fn device_local_heap_size(&self) -> u64 {
+        (0..self.memory_properties.memoryHeapCount as usize)
+            .map(|i| self.memory_properties.memoryHeaps[i])
+            .filter(|heap| heap.flags & vk::MEMORY_HEAP_DEVICE_LOCAL_BIT != 0)
+            .map(|heap| heap.size)
+            .sum()
+    }
+
+    fn device_type_rank(&self) -> u32 {
+        match self.properties.deviceType {
+            vk::PHYSICAL_DEVICE_TYPE_DISCRETE_GPU => 4,
+            vk::PHYSICAL_DEVICE_TYPE_INTEGRATED_GPU => 3,
+            vk::PHYSICAL_DEVICE_TYPE_VIRTUAL_GPU => 2,
+            vk::PHYSICAL_DEVICE_TYPE_CPU => 1,
+            _ => 0,
+        }
+    }
+
+    /// Rejects candidates missing a required extension or a queue family supporting
+    /// `required_queue_flags`, then ranks the rest by device type (discrete first)
+    /// and total DEVICE_LOCAL heap size.
+    pub fn select_best<'a>(
+        candidates: &'a [PhysicalDevice],
+        required_extensions: &DeviceExtensions,
+        required_queue_flags: vk::QueueFlags,
+    ) -> Option<&'a PhysicalDevice> {
+        candidates
+            .iter()
+            .filter(|d| required_extensions.difference(&d.extensions).is_empty())
+            .filter(|d| {
+                d.queue_family_properties
+                    .iter()
+                    .any(|q| q.queueFlags & required_queue_flags == required_queue_flags)
+            })
+            .max_by_key(|d| (d.device_type_rank(), d.device_local_heap_size()))
+    }
+
+    /// Resolves distinct graphics/compute/transfer/present queue family indices,
+    /// preferring dedicated transfer/compute families over a combined one and
+    /// falling back to a combined family when no dedicated one exists.
+    pub fn find_queue_families(
+        &self,
+        instance: &Instance,
+        surface: Option<vk::SurfaceKHR>,
+    ) -> QueueFamilyIndices {
+        let families = &self.queue_family_properties;
+        let has_flags = |q: &vk::QueueFamilyProperties, flags: vk::QueueFlags| q.queueFlags & flags == flags;
+
+        let transfer = families
+            .iter()
+            .position(|q| {
+                has_flags(q, vk::QUEUE_TRANSFER_BIT)
+                    && q.queueFlags & (vk::QUEUE_GRAPHICS_BIT | vk::QUEUE_COMPUTE_BIT) == 0
+            })
+            .or_else(|| families.iter().position(|q| has_flags(q, vk::QUEUE_TRANSFER_BIT)))
+            .map(|i| i as u32);
+
+        let compute = families
+            .iter()
+            .position(|q| has_flags(q, vk::QUEUE_COMPUTE_BIT) && q.queueFlags & vk::QUEUE_GRAPHICS_BIT == 0)
+            .or_else(|| families.iter().position(|q| has_flags(q, vk::QUEUE_COMPUTE_BIT)))
+            .map(|i| i as u32);
+
+        let graphics = families
+            .iter()
+            .position(|q| has_flags(q, vk::QUEUE_GRAPHICS_BIT))
+            .map(|i| i as u32);
+
+        let present = surface.and_then(|surface| {
+            families
+                .iter()
+                .enumerate()
+                .find(|(i, _)| self.supports_present(instance, *i as u32, surface))
+                .map(|(i, _)| i as u32)
+        });
+
+        QueueFamilyIndices {
+            graphics,
+            compute,
+            transfer,
+            present,
+        }
+    }
+
+    fn supports_present(
+        &self,
+        instance: &Instance,
+        queue_family_index: u32,
+        surface: vk::SurfaceKHR,
+    ) -> bool {
+        let get_support_fn: FnGetPhysicalDeviceSurfaceSupportKHR = unsafe {
+            match (instance.vk.GetInstanceProcAddr)(
+                instance.instance,
+                b"vkGetPhysicalDeviceSurfaceSupportKHR\0".as_ptr() as _,
+            ) {
+                Some(s) => mem::transmute(s),
+                None => return false,
+            }
+        };
+
+        let mut supported: vk::Bool32 = vk::FALSE;
+        let result =
+            unsafe { get_support_fn(self.handle, queue_family_index, surface, &mut supported) };
+        result == vk::SUCCESS && supported == vk::TRUE
+    }
+}
+
+type FnGetPhysicalDeviceSurfaceSupportKHR = unsafe extern "system" fn(
+    physicalDevice: vk::PhysicalDevice,
+    queueFamilyIndex: u32,
+    surface: vk::SurfaceKHR,
+    pSupported: *mut vk::Bool32,
+) -> vk::Result;
+
+/// Distinct queue family indices for the operations a renderer typically needs,
+/// resolved by `PhysicalDevice::find_queue_families`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct QueueFamilyIndices {
+    pub graphics: Option<u32>,
+    pub compute: Option<u32>,
+    pub transfer: Option<u32>,
+    pub present: Option<u32>,
+}
+
+impl QueueFamilyIndices {
+    pub fn is_complete(&self) -> bool {
+        self.graphics.is_some() && self.present.is_some()
+    }
+
+    /// Turns the resolved indices into the deduplicated `(family_index, queue_count)`
+    /// slice expected by `Device::new`.
+    pub fn to_queue_infos(&self, queue_family_properties: &[vk::QueueFamilyProperties]) -> Vec<(u32, u32)> {
+        let mut families: Vec<u32> = [self.graphics, self.compute, self.transfer, self.present]
+            .into_iter()
+            .flatten()
+            .collect();
+        families.sort_unstable();
+        families.dedup();
+        families
+            .into_iter()
+            .map(|i| (i, queue_family_properties[i as usize].queueCount))
+            .collect()
+    }
 }
 
 #[repr(transparent)]
@@ -455,6 +1032,7 @@ impl fmt::Display for VkMemoryType {
 
 pub struct Device {
     pub device: vk::Device,
+    pub GetDeviceProcAddr: vk::FnGetDeviceProcAddr,
     pub commands: vk::DeviceCommands,
 }
 
@@ -465,13 +1043,18 @@ impl Drop for Device {
 }
 
 impl Device {
+    /// `features2`, when given, is linked into `DeviceCreateInfo.pNext` so the enabled
+    /// subset queried via `Instance::get_physical_device_features2` actually reaches
+    /// `vkCreateDevice`; per spec `pEnabledFeatures` must then be null, so `features`
+    /// is ignored in that case.
     pub fn new(
         instance: &Instance,
         physical_device: vk::PhysicalDevice,
-        extensions: &[&str],
+        extensions: &DeviceExtensions,
         queues: &[(u32, u32)], // family index, queue count
         features: Option<vk::PhysicalDeviceFeatures>,
-    ) -> Result<Device, vk::Result> {
+        features2: Option<&FeaturesChain>,
+    ) -> Result<Device, VulkanError> {
         let max_queues = queues.iter().fold(0u32, |max, (_, x)| *x.max(&max));
         let priorities = vec![1.0; max_queues as usize];
         let queue_infos: Vec<_> = queues
@@ -486,16 +1069,15 @@ impl Device {
             })
             .collect();
 
-        let extensions_cstr: Vec<_> = extensions
-            .iter()
-            .map(|&s| CString::new(s).unwrap())
-            .collect();
-
+        let extensions_cstr = extensions.to_cstr_list();
         let extensions_ptr: Vec<_> = extensions_cstr.iter().map(|s| s.as_ptr()).collect();
 
         let info = vk::DeviceCreateInfo {
             sType: vk::STRUCTURE_TYPE_DEVICE_CREATE_INFO,
-            pNext: ptr::null(),
+            pNext: match features2 {
+                Some(chain) => &chain.features2 as *const _ as *const std::ffi::c_void,
+                None => ptr::null(),
+            },
             flags: 0,
             queueCreateInfoCount: queue_infos.len() as _,
             pQueueCreateInfos: queue_infos.as_ptr(),
@@ -503,26 +1085,533 @@ impl Device {
             ppEnabledLayerNames: ptr::null(),
             enabledExtensionCount: extensions_ptr.len() as _,
             ppEnabledExtensionNames: extensions_ptr.as_ptr(),
-            pEnabledFeatures: match features {
-                Some(f) => &f,
-                _ => ptr::null(),
+            pEnabledFeatures: if features2.is_some() {
+                ptr::null()
+            } else {
+                match features {
+                    Some(f) => &f,
+                    _ => ptr::null(),
+                }
             },
         };
 
         let mut device: vk::Device = 0;
-        let result = unsafe {
+        check(unsafe {
             instance
                 .commands
                 .CreateDevice(physical_device, &info, ptr::null(), &mut device)
+        })?;
+
+        let commands = vk::DeviceCommands::new(instance.commands.GetDeviceProcAddr, device);
+        Ok(Device {
+            device,
+            GetDeviceProcAddr: instance.commands.GetDeviceProcAddr,
+            commands,
+        })
+    }
+}
+
+type FnDestroySurfaceKHR = unsafe extern "system" fn(
+    instance: vk::Instance,
+    surface: vk::SurfaceKHR,
+    pAllocator: *const vk::AllocationCallbacks,
+);
+
+#[cfg(windows)]
+type FnCreateWin32SurfaceKHR = unsafe extern "system" fn(
+    instance: vk::Instance,
+    pCreateInfo: *const vk::Win32SurfaceCreateInfoKHR,
+    pAllocator: *const vk::AllocationCallbacks,
+    pSurface: *mut vk::SurfaceKHR,
+) -> vk::Result;
+
+#[cfg(target_os = "linux")]
+type FnCreateXlibSurfaceKHR = unsafe extern "system" fn(
+    instance: vk::Instance,
+    pCreateInfo: *const vk::XlibSurfaceCreateInfoKHR,
+    pAllocator: *const vk::AllocationCallbacks,
+    pSurface: *mut vk::SurfaceKHR,
+) -> vk::Result;
+
+#[cfg(target_os = "linux")]
+type FnCreateWaylandSurfaceKHR = unsafe extern "system" fn(
+    instance: vk::Instance,
+    pCreateInfo: *const vk::WaylandSurfaceCreateInfoKHR,
+    pAllocator: *const vk::AllocationCallbacks,
+    pSurface: *mut vk::SurfaceKHR,
+) -> vk::Result;
+
+/// Owns a `VkSurfaceKHR`, created from a raw window handle via the platform-specific
+/// `VK_KHR_*_surface` extension, loaded manually through `GetInstanceProcAddr`.
+pub struct Surface {
+    instance: vk::Instance,
+    destroy_fn: FnDestroySurfaceKHR,
+    pub surface: vk::SurfaceKHR,
+}
+
+impl Drop for Surface {
+    fn drop(&mut self) {
+        unsafe { (self.destroy_fn)(self.instance, self.surface, ptr::null()) };
+    }
+}
+
+impl Surface {
+    fn load_destroy_fn(instance: &Instance) -> Result<FnDestroySurfaceKHR, VulkanError> {
+        unsafe {
+            let s = (instance.vk.GetInstanceProcAddr)(
+                instance.instance,
+                b"vkDestroySurfaceKHR\0".as_ptr() as _,
+            )
+            .ok_or(VulkanError::ExtensionNotPresent)?;
+            Ok(mem::transmute(s))
+        }
+    }
+
+    #[cfg(windows)]
+    pub fn new_win32(
+        instance: &Instance,
+        hinstance: *mut std::ffi::c_void,
+        hwnd: *mut std::ffi::c_void,
+    ) -> Result<Self, VulkanError> {
+        let create_fn: FnCreateWin32SurfaceKHR = unsafe {
+            mem::transmute(
+                (instance.vk.GetInstanceProcAddr)(
+                    instance.instance,
+                    b"vkCreateWin32SurfaceKHR\0".as_ptr() as _,
+                )
+                .ok_or(VulkanError::ExtensionNotPresent)?,
+            )
         };
+        let destroy_fn = Self::load_destroy_fn(instance)?;
 
-        match result {
-            vk::SUCCESS => {
-                let commands = vk::DeviceCommands::new(instance.commands.GetDeviceProcAddr, device);
-                Ok(Device { device, commands })
+        let info = vk::Win32SurfaceCreateInfoKHR {
+            sType: vk::STRUCTURE_TYPE_WIN32_SURFACE_CREATE_INFO_KHR,
+            pNext: ptr::null(),
+            flags: 0,
+            hinstance,
+            hwnd,
+        };
+
+        let mut surface: vk::SurfaceKHR = 0;
+        check(unsafe { create_fn(instance.instance, &info, ptr::null(), &mut surface) })?;
+
+        Ok(Surface {
+            instance: instance.instance,
+            destroy_fn,
+            surface,
+        })
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn new_xlib(
+        instance: &Instance,
+        display: *mut vk::Display,
+        window: vk::Window,
+    ) -> Result<Self, VulkanError> {
+        let create_fn: FnCreateXlibSurfaceKHR = unsafe {
+            mem::transmute(
+                (instance.vk.GetInstanceProcAddr)(
+                    instance.instance,
+                    b"vkCreateXlibSurfaceKHR\0".as_ptr() as _,
+                )
+                .ok_or(VulkanError::ExtensionNotPresent)?,
+            )
+        };
+        let destroy_fn = Self::load_destroy_fn(instance)?;
+
+        let info = vk::XlibSurfaceCreateInfoKHR {
+            sType: vk::STRUCTURE_TYPE_XLIB_SURFACE_CREATE_INFO_KHR,
+            pNext: ptr::null(),
+            flags: 0,
+            dpy: display,
+            window,
+        };
+
+        let mut surface: vk::SurfaceKHR = 0;
+        check(unsafe { create_fn(instance.instance, &info, ptr::null(), &mut surface) })?;
+
+        Ok(Surface {
+            instance: instance.instance,
+            destroy_fn,
+            surface,
+        })
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn new_wayland(
+        instance: &Instance,
+        display: *mut vk::wl_display,
+        wayland_surface: *mut vk::wl_surface,
+    ) -> Result<Self, VulkanError> {
+        let create_fn: FnCreateWaylandSurfaceKHR = unsafe {
+            mem::transmute(
+                (instance.vk.GetInstanceProcAddr)(
+                    instance.instance,
+                    b"vkCreateWaylandSurfaceKHR\0".as_ptr() as _,
+                )
+                .ok_or(VulkanError::ExtensionNotPresent)?,
+            )
+        };
+        let destroy_fn = Self::load_destroy_fn(instance)?;
+
+        let info = vk::WaylandSurfaceCreateInfoKHR {
+            sType: vk::STRUCTURE_TYPE_WAYLAND_SURFACE_CREATE_INFO_KHR,
+            pNext: ptr::null(),
+            flags: 0,
+            display,
+            surface: wayland_surface,
+        };
+
+        let mut surface: vk::SurfaceKHR = 0;
+        check(unsafe { create_fn(instance.instance, &info, ptr::null(), &mut surface) })?;
+
+        Ok(Surface {
+            instance: instance.instance,
+            destroy_fn,
+            surface,
+        })
+    }
+}
+
+type FnGetPhysicalDeviceSurfaceCapabilitiesKHR = unsafe extern "system" fn(
+    physicalDevice: vk::PhysicalDevice,
+    surface: vk::SurfaceKHR,
+    pSurfaceCapabilities: *mut vk::SurfaceCapabilitiesKHR,
+) -> vk::Result;
+
+type FnGetPhysicalDeviceSurfaceFormatsKHR = unsafe extern "system" fn(
+    physicalDevice: vk::PhysicalDevice,
+    surface: vk::SurfaceKHR,
+    pSurfaceFormatCount: *mut u32,
+    pSurfaceFormats: *mut vk::SurfaceFormatKHR,
+) -> vk::Result;
+
+type FnGetPhysicalDeviceSurfacePresentModesKHR = unsafe extern "system" fn(
+    physicalDevice: vk::PhysicalDevice,
+    surface: vk::SurfaceKHR,
+    pPresentModeCount: *mut u32,
+    pPresentModes: *mut vk::PresentModeKHR,
+) -> vk::Result;
+
+type FnCreateSwapchainKHR = unsafe extern "system" fn(
+    device: vk::Device,
+    pCreateInfo: *const vk::SwapchainCreateInfoKHR,
+    pAllocator: *const vk::AllocationCallbacks,
+    pSwapchain: *mut vk::SwapchainKHR,
+) -> vk::Result;
+
+type FnDestroySwapchainKHR = unsafe extern "system" fn(
+    device: vk::Device,
+    swapchain: vk::SwapchainKHR,
+    pAllocator: *const vk::AllocationCallbacks,
+);
+
+type FnGetSwapchainImagesKHR = unsafe extern "system" fn(
+    device: vk::Device,
+    swapchain: vk::SwapchainKHR,
+    pSwapchainImageCount: *mut u32,
+    pSwapchainImages: *mut vk::Image,
+) -> vk::Result;
+
+type FnAcquireNextImageKHR = unsafe extern "system" fn(
+    device: vk::Device,
+    swapchain: vk::SwapchainKHR,
+    timeout: u64,
+    semaphore: vk::Semaphore,
+    fence: vk::Fence,
+    pImageIndex: *mut u32,
+) -> vk::Result;
+
+type FnQueuePresentKHR =
+    unsafe extern "system" fn(queue: vk::Queue, pPresentInfo: *const vk::PresentInfoKHR) -> vk::Result;
+
+fn load_instance_fn<F>(
+    get_instance_proc_addr: vk::FnGetInstanceProcAddr,
+    instance: vk::Instance,
+    name: &[u8],
+) -> Result<F, VulkanError> {
+    unsafe {
+        let addr = get_instance_proc_addr(instance, name.as_ptr() as _)
+            .ok_or(VulkanError::ExtensionNotPresent)?;
+        Ok(mem::transmute_copy(&addr))
+    }
+}
+
+fn load_device_fn<F>(
+    get_device_proc_addr: vk::FnGetDeviceProcAddr,
+    device: vk::Device,
+    name: &[u8],
+) -> Result<F, VulkanError> {
+    unsafe {
+        let addr =
+            get_device_proc_addr(device, name.as_ptr() as _).ok_or(VulkanError::ExtensionNotPresent)?;
+        Ok(mem::transmute_copy(&addr))
+    }
+}
+
+/// Swapchain image/semaphore/extent bookkeeping: picks an sRGB surface format
+/// (B8G8R8A8_SRGB with SRGB_NONLINEAR, else the first format the surface reports)
+/// and a MAILBOX-preferred/FIFO-fallback present mode, then exposes `acquire_next_image`
+/// and `present` helpers with one acquisition semaphore per swapchain image.
+pub struct Swapchain {
+    device: vk::Device,
+    get_device_proc_addr: vk::FnGetDeviceProcAddr,
+    destroy_fn: FnDestroySwapchainKHR,
+    acquire_fn: FnAcquireNextImageKHR,
+    present_fn: FnQueuePresentKHR,
+    pub swapchain: vk::SwapchainKHR,
+    pub format: vk::Format,
+    pub extent: vk::Extent2D,
+    pub images: Vec<vk::Image>,
+    image_available_semaphores: Vec<vk::Semaphore>,
+    next_semaphore: usize,
+}
+
+impl Drop for Swapchain {
+    fn drop(&mut self) {
+        unsafe { (self.destroy_fn)(self.device, self.swapchain, ptr::null()) };
+    }
+}
+
+impl Swapchain {
+    pub fn new(
+        instance: &Instance,
+        physical_device: vk::PhysicalDevice,
+        device: &Device,
+        surface: &Surface,
+        extent: vk::Extent2D,
+    ) -> Result<Self, VulkanError> {
+        let get_capabilities_fn: FnGetPhysicalDeviceSurfaceCapabilitiesKHR = load_instance_fn(
+            instance.vk.GetInstanceProcAddr,
+            instance.instance,
+            b"vkGetPhysicalDeviceSurfaceCapabilitiesKHR\0",
+        )?;
+        let get_formats_fn: FnGetPhysicalDeviceSurfaceFormatsKHR = load_instance_fn(
+            instance.vk.GetInstanceProcAddr,
+            instance.instance,
+            b"vkGetPhysicalDeviceSurfaceFormatsKHR\0",
+        )?;
+        let get_present_modes_fn: FnGetPhysicalDeviceSurfacePresentModesKHR = load_instance_fn(
+            instance.vk.GetInstanceProcAddr,
+            instance.instance,
+            b"vkGetPhysicalDeviceSurfacePresentModesKHR\0",
+        )?;
+        let create_swapchain_fn: FnCreateSwapchainKHR = load_device_fn(
+            device.GetDeviceProcAddr,
+            device.device,
+            b"vkCreateSwapchainKHR\0",
+        )?;
+        let get_images_fn: FnGetSwapchainImagesKHR = load_device_fn(
+            device.GetDeviceProcAddr,
+            device.device,
+            b"vkGetSwapchainImagesKHR\0",
+        )?;
+        let acquire_fn: FnAcquireNextImageKHR = load_device_fn(
+            device.GetDeviceProcAddr,
+            device.device,
+            b"vkAcquireNextImageKHR\0",
+        )?;
+        let present_fn: FnQueuePresentKHR =
+            load_device_fn(device.GetDeviceProcAddr, device.device, b"vkQueuePresentKHR\0")?;
+        let destroy_fn: FnDestroySwapchainKHR = load_device_fn(
+            device.GetDeviceProcAddr,
+            device.device,
+            b"vkDestroySwapchainKHR\0",
+        )?;
+
+        let mut capabilities = std::mem::MaybeUninit::<vk::SurfaceCapabilitiesKHR>::uninit();
+        check(unsafe {
+            get_capabilities_fn(physical_device, surface.surface, capabilities.as_mut_ptr())
+        })?;
+        let capabilities = unsafe { capabilities.assume_init() };
+
+        let mut format_count: u32 = 0;
+        unsafe {
+            get_formats_fn(physical_device, surface.surface, &mut format_count, ptr::null_mut());
+        }
+        let mut formats = vec![
+            unsafe { std::mem::MaybeUninit::<vk::SurfaceFormatKHR>::uninit().assume_init() };
+            format_count as usize
+        ];
+        unsafe {
+            get_formats_fn(
+                physical_device,
+                surface.surface,
+                &mut format_count,
+                formats.as_mut_ptr(),
+            );
+        }
+        let chosen_format = formats
+            .iter()
+            .find(|f| {
+                f.format == vk::FORMAT_B8G8R8A8_SRGB
+                    && f.colorSpace == vk::COLOR_SPACE_SRGB_NONLINEAR_KHR
+            })
+            .or_else(|| formats.first())
+            .copied()
+            .ok_or(VulkanError::InitializationFailed)?;
+
+        let mut present_mode_count: u32 = 0;
+        unsafe {
+            get_present_modes_fn(
+                physical_device,
+                surface.surface,
+                &mut present_mode_count,
+                ptr::null_mut(),
+            );
+        }
+        let mut present_modes = vec![0; present_mode_count as usize];
+        unsafe {
+            get_present_modes_fn(
+                physical_device,
+                surface.surface,
+                &mut present_mode_count,
+                present_modes.as_mut_ptr(),
+            );
+        }
+        let present_mode = if present_modes.contains(&vk::PRESENT_MODE_MAILBOX_KHR) {
+            vk::PRESENT_MODE_MAILBOX_KHR
+        } else {
+            vk::PRESENT_MODE_FIFO_KHR
+        };
+
+        let image_count = if capabilities.maxImageCount > 0 {
+            (capabilities.minImageCount + 1).min(capabilities.maxImageCount)
+        } else {
+            capabilities.minImageCount + 1
+        };
+
+        // A currentExtent of (0xFFFFFFFF, 0xFFFFFFFF) means the surface lets us pick;
+        // otherwise it dictates the extent. Either way, imageExtent must fall within
+        // min/maxImageExtent or vkCreateSwapchainKHR is invalid usage.
+        let extent = if capabilities.currentExtent.width != u32::MAX {
+            capabilities.currentExtent
+        } else {
+            vk::Extent2D {
+                width: extent
+                    .width
+                    .clamp(capabilities.minImageExtent.width, capabilities.maxImageExtent.width),
+                height: extent.height.clamp(
+                    capabilities.minImageExtent.height,
+                    capabilities.maxImageExtent.height,
+                ),
+            }
+        };
+
+        let info = vk::SwapchainCreateInfoKHR {
+            sType: vk::STRUCTURE_TYPE_SWAPCHAIN_CREATE_INFO_KHR,
+            pNext: ptr::null(),
+            flags: 0,
+            surface: surface.surface,
+            minImageCount: image_count,
+            imageFormat: chosen_format.format,
+            imageColorSpace: chosen_format.colorSpace,
+            imageExtent: extent,
+            imageArrayLayers: 1,
+            imageUsage: vk::IMAGE_USAGE_COLOR_ATTACHMENT_BIT,
+            imageSharingMode: vk::SHARING_MODE_EXCLUSIVE,
+            queueFamilyIndexCount: 0,
+            pQueueFamilyIndices: ptr::null(),
+            preTransform: capabilities.currentTransform,
+            compositeAlpha: vk::COMPOSITE_ALPHA_OPAQUE_BIT_KHR,
+            presentMode: present_mode,
+            clipped: vk::TRUE,
+            oldSwapchain: 0,
+        };
+
+        let mut swapchain: vk::SwapchainKHR = 0;
+        check(unsafe { create_swapchain_fn(device.device, &info, ptr::null(), &mut swapchain) })?;
+
+        let mut image_count: u32 = 0;
+        unsafe {
+            get_images_fn(device.device, swapchain, &mut image_count, ptr::null_mut());
+        }
+        let mut images = vec![0; image_count as usize];
+        unsafe {
+            get_images_fn(device.device, swapchain, &mut image_count, images.as_mut_ptr());
+        }
+
+        let semaphore_info = vk::SemaphoreCreateInfo {
+            sType: vk::STRUCTURE_TYPE_SEMAPHORE_CREATE_INFO,
+            pNext: ptr::null(),
+            flags: 0,
+        };
+        // Built up imperatively, rather than via collect::<Result<Vec<_>, _>>(), so that
+        // a failure partway through can tear down the semaphores already created and the
+        // swapchain itself instead of leaking them (nothing owns them yet to do it for us).
+        let mut image_available_semaphores = Vec::with_capacity(images.len());
+        for _ in &images {
+            let mut semaphore: vk::Semaphore = 0;
+            let result = unsafe {
+                device
+                    .commands
+                    .CreateSemaphore(device.device, &semaphore_info, ptr::null(), &mut semaphore)
+            };
+            if let Err(e) = check(result) {
+                for semaphore in image_available_semaphores {
+                    unsafe { device.commands.DestroySemaphore(device.device, semaphore, ptr::null()) };
+                }
+                unsafe { destroy_fn(device.device, swapchain, ptr::null()) };
+                return Err(e);
             }
-            _ => Err(result),
+            image_available_semaphores.push(semaphore);
         }
+
+        Ok(Swapchain {
+            device: device.device,
+            get_device_proc_addr: device.GetDeviceProcAddr,
+            destroy_fn,
+            acquire_fn,
+            present_fn,
+            swapchain,
+            format: chosen_format.format,
+            extent,
+            images,
+            image_available_semaphores,
+            next_semaphore: 0,
+        })
+    }
+
+    /// Acquires the next available image, cycling through one semaphore per
+    /// swapchain image. Returns the image index and the semaphore that will be
+    /// signalled once the image is actually available.
+    pub fn acquire_next_image(&mut self) -> Result<(u32, vk::Semaphore), VulkanError> {
+        let semaphore = self.image_available_semaphores[self.next_semaphore];
+        self.next_semaphore = (self.next_semaphore + 1) % self.image_available_semaphores.len();
+
+        let mut image_index: u32 = 0;
+        check(unsafe {
+            (self.acquire_fn)(
+                self.device,
+                self.swapchain,
+                u64::MAX,
+                semaphore,
+                0,
+                &mut image_index,
+            )
+        })?;
+
+        Ok((image_index, semaphore))
+    }
+
+    pub fn present(
+        &self,
+        queue: vk::Queue,
+        image_index: u32,
+        wait_semaphore: vk::Semaphore,
+    ) -> Result<(), VulkanError> {
+        let info = vk::PresentInfoKHR {
+            sType: vk::STRUCTURE_TYPE_PRESENT_INFO_KHR,
+            pNext: ptr::null(),
+            waitSemaphoreCount: 1,
+            pWaitSemaphores: &wait_semaphore,
+            swapchainCount: 1,
+            pSwapchains: &self.swapchain,
+            pImageIndices: &image_index,
+            pResults: ptr::null_mut(),
+        };
+
+        check(unsafe { (self.present_fn)(queue, &info) })
     }
 }
 
@@ -546,84 +1635,91 @@ fn main() {
         );
     }
 
+    let requested_extensions = InstanceExtensions {
+        ext_debug_utils: true,
+        khr_surface: true,
+        ..Default::default()
+    };
+    let supported_extensions = InstanceExtensions::from_enumerated(&extensions);
+    let unsupported = requested_extensions.difference(&supported_extensions);
+    if !unsupported.is_empty() {
+        println!("unsupported instance extensions: {:?}", unsupported);
+    }
+
     let instance = Instance::new(
         vulkan,
         "app",
         "engine",
         &["VK_LAYER_KHRONOS_validation"],
-        &["VK_EXT_debug_utils", "VK_KHR_surface"],
+        &requested_extensions,
+        (1, 2, 133),
+        Some(DebugMessengerInfo::new(Box::new(default_debug_callback))),
     )
     .unwrap();
-    let physical_devices = instance.enum_physical_devices().unwrap();
-    for &d in &physical_devices {
-        let properties = instance.get_physical_device_properties(d);
-        //let features = instance.get_physical_device_features(d);
-        let queue_family_props = instance.enum_physical_device_queue_family_properties(d);
-
-        let ver = vk::get_version(properties.apiVersion);
+    let physical_devices = instance.enumerate_physical_devices_cached().unwrap();
+    for d in &physical_devices {
+        let ver = vk::get_version(d.properties.apiVersion);
         println!(
             "device: {} ({},{},{})",
-            VkPhysicalDeviceProperties(properties),
+            VkPhysicalDeviceProperties(d.properties),
             ver.0,
             ver.1,
             ver.2
         );
         println!("  queue families:");
-        for &q in &queue_family_props {
+        for &q in &d.queue_family_properties {
             println!("    {}", VkQueueFamilyProperties(q));
         }
 
-        let mem_properties = instance.get_physical_device_memory_properties(d);
-
         println!("  memory_types:");
-        for i in 0..mem_properties.memoryTypeCount {
+        for i in 0..d.memory_properties.memoryTypeCount {
             println!(
                 "    {}",
-                VkMemoryType(mem_properties.memoryTypes[i as usize])
+                VkMemoryType(d.memory_properties.memoryTypes[i as usize])
             );
         }
         println!("  memory_heaps:");
-        for i in 0..mem_properties.memoryHeapCount {
+        for i in 0..d.memory_properties.memoryHeapCount {
             println!(
                 "    {}",
-                VkMemoryHeap(mem_properties.memoryHeaps[i as usize])
+                VkMemoryHeap(d.memory_properties.memoryHeaps[i as usize])
             );
         }
 
-        println!("  extensions:");
-        let extensions = instance.enum_physical_device_extensions(d).unwrap();
-        for e in &extensions {
-            println!("    {}", e.extension_name());
-        }
+        println!("  extensions: {:?}", d.extensions);
     }
 
-    let physical_device = physical_devices[0];
-    let queue_family_props = instance.enum_physical_device_queue_family_properties(physical_device);
-    let queues: Vec<_> = queue_family_props
-        .iter()
-        .enumerate()
-        .map(|(i, q)| (i as u32, q.queueCount))
-        .collect();
+    let required_device_extensions = DeviceExtensions {
+        khr_swapchain: true,
+    };
+    let physical_device = PhysicalDevice::select_best(
+        &physical_devices,
+        &required_device_extensions,
+        vk::QUEUE_GRAPHICS_BIT,
+    )
+    .expect("no suitable physical device");
+
+    let queue_family_indices = physical_device.find_queue_families(&instance, None);
+    let queues = queue_family_indices.to_queue_infos(&physical_device.queue_family_properties);
 
     let device = Device::new(
         &instance,
-        physical_device,
-        &["VK_KHR_swapchain"],
+        physical_device.handle,
+        &required_device_extensions,
         &queues,
         None,
+        None,
     )
     .unwrap();
 
-    let graphics_queue = queue_family_props
-        .iter()
-        .enumerate()
-        .find(|(_, q)| q.queueFlags & vk::QUEUE_GRAPHICS_BIT != 0)
-        .unwrap();
     let mut queue: vk::Queue = 0;
     unsafe {
-        device
-            .commands
-            .GetDeviceQueue(device.device, graphics_queue.0 as _, 0, &mut queue);
+        device.commands.GetDeviceQueue(
+            device.device,
+            queue_family_indices.graphics.unwrap(),
+            0,
+            &mut queue,
+        );
     }
 
     unsafe {
@@ -632,3 +1728,179 @@ fn main() {
 
     println!("done");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue_family(flags: vk::QueueFlags, count: u32) -> vk::QueueFamilyProperties {
+        let mut props: vk::QueueFamilyProperties = unsafe { mem::zeroed() };
+        props.queueFlags = flags;
+        props.queueCount = count;
+        props
+    }
+
+    fn physical_device(
+        device_type: vk::PhysicalDeviceType,
+        device_local_heap_size: u64,
+        extensions: DeviceExtensions,
+        queue_family_properties: Vec<vk::QueueFamilyProperties>,
+    ) -> PhysicalDevice {
+        let mut properties: vk::PhysicalDeviceProperties = unsafe { mem::zeroed() };
+        properties.deviceType = device_type;
+
+        let mut memory_properties: vk::PhysicalDeviceMemoryProperties = unsafe { mem::zeroed() };
+        memory_properties.memoryHeapCount = 1;
+        memory_properties.memoryHeaps[0] = vk::MemoryHeap {
+            size: device_local_heap_size,
+            flags: vk::MEMORY_HEAP_DEVICE_LOCAL_BIT,
+        };
+
+        PhysicalDevice {
+            handle: 0,
+            properties,
+            features: unsafe { mem::zeroed() },
+            memory_properties,
+            queue_family_properties,
+            extensions,
+        }
+    }
+
+    #[test]
+    fn select_best_prefers_discrete_over_integrated() {
+        let integrated = physical_device(
+            vk::PHYSICAL_DEVICE_TYPE_INTEGRATED_GPU,
+            1 << 34, // 16 GiB, larger than the discrete candidate below
+            DeviceExtensions::default(),
+            vec![queue_family(vk::QUEUE_GRAPHICS_BIT, 1)],
+        );
+        let discrete = physical_device(
+            vk::PHYSICAL_DEVICE_TYPE_DISCRETE_GPU,
+            1 << 30, // 1 GiB
+            DeviceExtensions::default(),
+            vec![queue_family(vk::QUEUE_GRAPHICS_BIT, 1)],
+        );
+        let candidates = [integrated, discrete];
+
+        let best = PhysicalDevice::select_best(
+            &candidates,
+            &DeviceExtensions::default(),
+            vk::QUEUE_GRAPHICS_BIT,
+        )
+        .unwrap();
+
+        assert_eq!(best.properties.deviceType, vk::PHYSICAL_DEVICE_TYPE_DISCRETE_GPU);
+    }
+
+    #[test]
+    fn select_best_breaks_ties_by_device_local_heap_size() {
+        let smaller = physical_device(
+            vk::PHYSICAL_DEVICE_TYPE_DISCRETE_GPU,
+            1 << 30,
+            DeviceExtensions::default(),
+            vec![queue_family(vk::QUEUE_GRAPHICS_BIT, 1)],
+        );
+        let larger = physical_device(
+            vk::PHYSICAL_DEVICE_TYPE_DISCRETE_GPU,
+            1 << 31,
+            DeviceExtensions::default(),
+            vec![queue_family(vk::QUEUE_GRAPHICS_BIT, 1)],
+        );
+        let candidates = [smaller, larger];
+
+        let best = PhysicalDevice::select_best(
+            &candidates,
+            &DeviceExtensions::default(),
+            vk::QUEUE_GRAPHICS_BIT,
+        )
+        .unwrap();
+
+        assert_eq!(best.device_local_heap_size(), 1 << 31);
+    }
+
+    #[test]
+    fn select_best_rejects_missing_extension() {
+        let mut supported = DeviceExtensions::default();
+        supported.khr_swapchain = true;
+        let device = physical_device(
+            vk::PHYSICAL_DEVICE_TYPE_DISCRETE_GPU,
+            1 << 30,
+            DeviceExtensions::default(),
+            vec![queue_family(vk::QUEUE_GRAPHICS_BIT, 1)],
+        );
+        let candidates = [device];
+
+        assert!(PhysicalDevice::select_best(&candidates, &supported, vk::QUEUE_GRAPHICS_BIT).is_none());
+    }
+
+    #[test]
+    fn select_best_rejects_missing_queue_flags() {
+        let device = physical_device(
+            vk::PHYSICAL_DEVICE_TYPE_DISCRETE_GPU,
+            1 << 30,
+            DeviceExtensions::default(),
+            vec![queue_family(vk::QUEUE_TRANSFER_BIT, 1)],
+        );
+        let candidates = [device];
+
+        assert!(PhysicalDevice::select_best(
+            &candidates,
+            &DeviceExtensions::default(),
+            vk::QUEUE_GRAPHICS_BIT
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn extension_set_difference_and_intersection() {
+        let mut a = DeviceExtensions::default();
+        a.khr_swapchain = true;
+
+        let b = DeviceExtensions::default();
+
+        assert_eq!(a.difference(&b), a);
+        assert!(a.intersection(&b).is_empty());
+        assert!(a.difference(&a).is_empty());
+    }
+
+    #[test]
+    fn queue_family_indices_to_queue_infos_dedups_shared_family() {
+        let indices = QueueFamilyIndices {
+            graphics: Some(0),
+            compute: Some(0),
+            transfer: Some(1),
+            present: Some(0),
+        };
+        let queue_family_properties = vec![queue_family(vk::QUEUE_GRAPHICS_BIT, 2), queue_family(vk::QUEUE_TRANSFER_BIT, 1)];
+
+        let infos = indices.to_queue_infos(&queue_family_properties);
+
+        assert_eq!(infos, vec![(0, 2), (1, 1)]);
+    }
+
+    #[test]
+    fn queue_family_indices_is_complete_requires_graphics_and_present() {
+        assert!(!QueueFamilyIndices::default().is_complete());
+        assert!(!QueueFamilyIndices {
+            graphics: Some(0),
+            ..Default::default()
+        }
+        .is_complete());
+        assert!(QueueFamilyIndices {
+            graphics: Some(0),
+            present: Some(1),
+            ..Default::default()
+        }
+        .is_complete());
+    }
+
+    #[test]
+    fn vulkan_error_from_known_and_unknown_result() {
+        assert_eq!(
+            VulkanError::from(vk::ERROR_DEVICE_LOST),
+            VulkanError::DeviceLost
+        );
+        assert_eq!(VulkanError::from(vk::ERROR_OUT_OF_DATE_KHR), VulkanError::OutOfDateKHR);
+        assert_eq!(VulkanError::from(-1000), VulkanError::Unknown(-1000));
+    }
+}